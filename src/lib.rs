@@ -30,9 +30,24 @@
 //!
 //! # Notes
 //!
-//! - [get](GuardedKey::get) requires the value type to implement [Clone].
-//! - Accessing the value without having a guard will panic.
+//! - [get](GuardedKey::get) requires the value type to implement [Clone]. Use
+//!   [with](GuardedKey::with) to borrow the value instead, which has no such
+//!   bound.
+//! - Accessing the value without having a guard will panic. Use
+//!   [is_set](GuardedKey::is_set), [try_get](GuardedKey::try_get), or
+//!   [try_with](GuardedKey::try_with) for a non-panicking alternative.
+//! - [with_mut](GuardedKey::with_mut) hands out an exclusive reference to the
+//!   value, so it can be updated in place without popping and re-pushing a
+//!   guard.
 //! - Guards dropped out of order have well-defined behavior.
+//! - If the value does not live for `'static` (e.g. it is a local), or you
+//!   would rather not give up ownership of it at all, use
+//!   [guarded_scoped_thread_local] and [ScopedGuardedKey] instead, which
+//!   borrows the value for the duration of a closure instead of returning a
+//!   [Guard]. Its type must still be `'static`.
+//! - [Guard::into_inner] hands the value back to the caller instead of
+//!   discarding it, and [Guard::replace] swaps the value in place without
+//!   popping and re-pushing a guard.
 //!
 //! # See Also
 //!
@@ -61,7 +76,7 @@
 //!
 //! my_test();
 //! ```
-use std::{cell::RefCell, thread::LocalKey};
+use std::{cell::RefCell, marker::PhantomData, thread::LocalKey};
 
 #[macro_export]
 macro_rules! guarded_thread_local {
@@ -76,6 +91,22 @@ macro_rules! guarded_thread_local {
     )
 }
 
+/// Like [guarded_thread_local], but the key stores a borrowed reference for
+/// the duration of a call to [set](ScopedGuardedKey::set) instead of owning
+/// a `'static` value.
+#[macro_export]
+macro_rules! guarded_scoped_thread_local {
+    ($(#[$attrs:meta])* $vis:vis static $name:ident: $ty:ty) => (
+        $(#[$attrs])*
+        $vis static $name: $crate::ScopedGuardedKey<$ty> = {
+            ::std::thread_local!(static FOO: ::std::cell::RefCell<$crate::Inner<*const ()>> = const {
+                ::std::cell::RefCell::new($crate::Inner::new())
+            });
+            $crate::ScopedGuardedKey::new(&FOO)
+        };
+    )
+}
+
 /// A nested thread-local that spawns a [Guard] for each [set](GuardedKey::set).
 pub struct GuardedKey<T: 'static> {
     inner: &'static LocalKey<RefCell<Inner<T>>>,
@@ -122,6 +153,168 @@ impl<T: Clone + 'static> GuardedKey<T> {
         // until it finds a non-None entry.
         val.expect("internal error: top of item list is none")
     }
+
+    /// Clones and returns the last value of thread-local stack, or `None` if
+    /// this thread-local has not been [set](GuardedKey::set).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the [Clone] implementation of `T` accesses this same thread
+    /// local.
+    pub fn try_get(&'static self) -> Option<T> {
+        self.inner
+            .with_borrow(|inner| inner.item.last().cloned())
+            .map(|val| {
+                // The top of the stack cannot be None, as Guard::drop will pop from the stack
+                // until it finds a non-None entry.
+                val.expect("internal error: top of item list is none")
+            })
+    }
+}
+
+impl<T: 'static> GuardedKey<T> {
+    /// Borrows the last value of the thread-local stack and passes it to `f`.
+    ///
+    /// Unlike [get](GuardedKey::get), this does not require `T: Clone`, since
+    /// `f` only ever receives a shared reference to the value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this thread-local has not previously been
+    /// [set](GuardedKey::set).
+    ///
+    /// Panics if `f` calls [set](GuardedKey::set) on this same thread local.
+    pub fn with<R>(&'static self, f: impl FnOnce(&T) -> R) -> R {
+        self.inner.with_borrow(|inner| {
+            let Some(val) = inner.item.last() else {
+                panic!("cannot access a guarded thread local variable without calling `set` first")
+            };
+
+            // The top of the stack cannot be None, as Guard::drop will pop from the stack
+            // until it finds a non-None entry.
+            f(val
+                .as_ref()
+                .expect("internal error: top of item list is none"))
+        })
+    }
+
+    /// Returns whether this thread-local currently holds a live value, i.e.
+    /// whether [set](GuardedKey::set) has been called and the returned
+    /// [Guard] has not yet been dropped.
+    pub fn is_set(&'static self) -> bool {
+        self.inner.with_borrow(|inner| !inner.item.is_empty())
+    }
+
+    /// Borrows the last value of the thread-local stack and passes it to `f`,
+    /// returning `None` instead of panicking if this thread-local has not
+    /// been [set](GuardedKey::set).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` calls [set](GuardedKey::set) on this same thread local.
+    pub fn try_with<R>(&'static self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.inner.with_borrow(|inner| {
+            let val = inner.item.last()?;
+
+            // The top of the stack cannot be None, as Guard::drop will pop from the stack
+            // until it finds a non-None entry.
+            Some(f(val
+                .as_ref()
+                .expect("internal error: top of item list is none")))
+        })
+    }
+
+    /// Mutably borrows the last value of the thread-local stack and passes it
+    /// to `f`, allowing the value to be updated in place without popping and
+    /// re-pushing a new [Guard].
+    ///
+    /// # Panics
+    ///
+    /// Panics if this thread-local has not previously been
+    /// [set](GuardedKey::set).
+    ///
+    /// Panics if `f` calls [set](GuardedKey::set), [get](GuardedKey::get), or
+    /// any other accessor of this same thread local.
+    pub fn with_mut<R>(&'static self, f: impl FnOnce(&mut T) -> R) -> R {
+        self.inner.with_borrow_mut(|inner| {
+            let Some(val) = inner.item.last_mut() else {
+                panic!("cannot access a guarded thread local variable without calling `set` first")
+            };
+
+            // The top of the stack cannot be None, as Guard::drop will pop from the stack
+            // until it finds a non-None entry.
+            f(val
+                .as_mut()
+                .expect("internal error: top of item list is none"))
+        })
+    }
+}
+
+/// A nested thread-local that, for the duration of a call to
+/// [set](ScopedGuardedKey::set), stores a reference to a value owned by the
+/// caller rather than taking ownership of it.
+///
+/// Unlike with [GuardedKey], the value is not moved into the thread-local
+/// and does not have to implement [Clone]: [set](ScopedGuardedKey::set)
+/// borrows it from the caller, so it can be a local that does not live for
+/// `'static`. The type `T` itself still has to be `'static`, since the key is
+/// a `static`. The value is only accessible from within the closure passed
+/// to [set](ScopedGuardedKey::set).
+pub struct ScopedGuardedKey<T> {
+    inner: &'static LocalKey<RefCell<Inner<*const ()>>>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> ScopedGuardedKey<T> {
+    #[doc(hidden)]
+    pub const fn new(inner: &'static LocalKey<RefCell<Inner<*const ()>>>) -> Self {
+        Self {
+            inner,
+            marker: PhantomData,
+        }
+    }
+
+    /// Makes `t` accessible via [with](ScopedGuardedKey::with) for the
+    /// duration of `f`, restoring whatever was previously accessible (if
+    /// anything) once `f` returns or unwinds.
+    pub fn set<R>(&'static self, t: &T, f: impl FnOnce() -> R) -> R {
+        let ptr = t as *const T as *const ();
+        let _guard = self.inner.with_borrow_mut(move |inner| {
+            inner.item.push(Some(ptr));
+            Guard {
+                inner: self.inner,
+                index: inner.item.len() - 1,
+            }
+        });
+        f()
+    }
+
+    /// Borrows the value made accessible by the innermost enclosing call to
+    /// [set](ScopedGuardedKey::set) and passes it to `f`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a [set](ScopedGuardedKey::set) call.
+    ///
+    /// Panics if `f` calls [set](ScopedGuardedKey::set) on this same thread
+    /// local.
+    pub fn with<R>(&'static self, f: impl FnOnce(&T) -> R) -> R {
+        self.inner.with_borrow(|inner| {
+            let Some(ptr) = inner.item.last() else {
+                panic!("cannot access a guarded thread local variable without calling `set` first")
+            };
+
+            // The top of the stack cannot be None, as Guard::drop will pop from the stack
+            // until it finds a non-None entry.
+            let ptr = ptr.expect("internal error: top of item list is none");
+
+            // Safety: `ptr` was derived from the `&T` that `set` borrows for
+            // the duration of its closure, and is only ever popped from the
+            // stack once that closure returns or unwinds, so the pointee is
+            // still alive here.
+            f(unsafe { &*(ptr as *const T) })
+        })
+    }
 }
 
 #[doc(hidden)]
@@ -143,6 +336,47 @@ pub struct Guard<T: 'static> {
     index: usize,
 }
 
+impl<T> Guard<T> {
+    /// Consumes the guard, removing its slot from the thread-local stack and
+    /// handing the value that was stored there back to the caller, instead
+    /// of discarding it the way a normal drop would.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread-local is concurrently borrowed, e.g. from within
+    /// a [with](GuardedKey::with) or [with_mut](GuardedKey::with_mut) call.
+    pub fn into_inner(self) -> T {
+        let value = self.inner.with_borrow_mut(|inner| {
+            let value = inner.item.get_mut(self.index).unwrap().take().unwrap();
+
+            while let Some(item) = inner.item.last() {
+                if item.is_none() {
+                    let _ = inner.item.pop();
+                } else {
+                    break;
+                }
+            }
+
+            value
+        });
+
+        std::mem::forget(self);
+        value
+    }
+
+    /// Replaces the value in this guard's slot with `t`, returning the
+    /// previous value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread-local is concurrently borrowed, e.g. from within
+    /// a [with](GuardedKey::with) or [with_mut](GuardedKey::with_mut) call.
+    pub fn replace(&mut self, t: T) -> T {
+        self.inner
+            .with_borrow_mut(|inner| inner.item.get_mut(self.index).unwrap().replace(t).unwrap())
+    }
+}
+
 impl<T> Drop for Guard<T> {
     /// Removes associated value from the thread-local stack. If this is the
     /// last existing guard for this thread-local, then any
@@ -217,7 +451,163 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "already borrowed: BorrowMutError")]
+    fn into_inner_returns_value_and_restores_previous() {
+        guarded_thread_local!(static FOO: String);
+        let _guard_1 = FOO.set("x".into());
+        let guard_2 = FOO.set("y".into());
+
+        assert_eq!(guard_2.into_inner(), "y");
+        assert_eq!(FOO.get(), "x");
+    }
+
+    #[test]
+    fn replace_swaps_value_in_place() {
+        guarded_thread_local!(static FOO: u32);
+        let mut guard = FOO.set(1);
+
+        assert_eq!(guard.replace(2), 1);
+        assert_eq!(FOO.get(), 2);
+    }
+
+    #[test]
+    fn with_borrows_without_clone() {
+        guarded_thread_local!(static FOO: String);
+
+        let _guard = FOO.set("abc".into());
+        let len = FOO.with(|s| s.len());
+        assert_eq!(len, 3);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "cannot access a guarded thread local variable without calling `set` first"
+    )]
+    fn with_without_set() {
+        guarded_thread_local!(static FOO: u32);
+        FOO.with(|_| ());
+    }
+
+    #[test]
+    #[should_panic(expected = "borrowed")]
+    fn with_reentrant_set() {
+        guarded_thread_local!(static FOO: u32);
+        let _guard = FOO.set(1);
+        FOO.with(|_| {
+            let _ = FOO.set(2);
+        });
+    }
+
+    #[test]
+    fn is_set_reflects_guard_liveness() {
+        guarded_thread_local!(static FOO: u32);
+        assert!(!FOO.is_set());
+
+        let guard = FOO.set(1);
+        assert!(FOO.is_set());
+
+        drop(guard);
+        assert!(!FOO.is_set());
+    }
+
+    #[test]
+    fn try_get_without_set() {
+        guarded_thread_local!(static FOO: u32);
+        assert_eq!(FOO.try_get(), None);
+
+        let _guard = FOO.set(5);
+        assert_eq!(FOO.try_get(), Some(5));
+    }
+
+    #[test]
+    fn try_with_without_set() {
+        guarded_thread_local!(static FOO: String);
+        assert_eq!(FOO.try_with(|s| s.len()), None);
+
+        let _guard = FOO.set("abc".into());
+        assert_eq!(FOO.try_with(|s| s.len()), Some(3));
+    }
+
+    #[test]
+    fn with_mut_updates_in_place() {
+        guarded_thread_local!(static FOO: Vec<u32>);
+
+        let _guard = FOO.set(Vec::new());
+        FOO.with_mut(|v| v.push(1));
+        FOO.with_mut(|v| v.push(2));
+
+        assert_eq!(FOO.with(|v| v.clone()), vec![1, 2]);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "cannot access a guarded thread local variable without calling `set` first"
+    )]
+    fn with_mut_without_set() {
+        guarded_thread_local!(static FOO: u32);
+        FOO.with_mut(|_| ());
+    }
+
+    #[test]
+    #[should_panic(expected = "borrowed")]
+    fn with_mut_reentrant_get() {
+        guarded_thread_local!(static FOO: u32);
+        let _guard = FOO.set(1);
+        FOO.with_mut(|_| {
+            let _ = FOO.get();
+        });
+    }
+
+    #[test]
+    fn scoped_with_inside_set() {
+        guarded_scoped_thread_local!(static FOO: String);
+
+        let value = "abc".to_string();
+        FOO.set(&value, || {
+            FOO.with(|s| assert_eq!(s, "abc"));
+        });
+    }
+
+    #[test]
+    fn scoped_nested_set_restores_previous() {
+        guarded_scoped_thread_local!(static FOO: u32);
+
+        let outer = 1;
+        let inner = 2;
+        FOO.set(&outer, || {
+            FOO.with(|v| assert_eq!(*v, 1));
+
+            FOO.set(&inner, || {
+                FOO.with(|v| assert_eq!(*v, 2));
+            });
+
+            FOO.with(|v| assert_eq!(*v, 1));
+        });
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "cannot access a guarded thread local variable without calling `set` first"
+    )]
+    fn scoped_with_without_set() {
+        guarded_scoped_thread_local!(static FOO: u32);
+        FOO.with(|_| ());
+    }
+
+    #[test]
+    #[should_panic(expected = "borrowed")]
+    fn scoped_with_reentrant_set() {
+        guarded_scoped_thread_local!(static FOO: u32);
+        let outer = 1;
+        let inner = 2;
+        FOO.set(&outer, || {
+            FOO.with(|_| {
+                FOO.set(&inner, || ());
+            });
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "borrowed")]
     fn clone_access_same_thread_local() {
         guarded_thread_local!(static FOO: X);
 